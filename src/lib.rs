@@ -1,5 +1,6 @@
 use crate::RemoveCallbackError::{NonexistentCallback, NonexistentCell};
-use std::collections::{HashMap, VecDeque};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 /// `InputCellId` is a unique identifier for an input cell.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -37,21 +38,66 @@ pub enum RemoveCallbackError {
     NonexistentCallback,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum SetDependenciesError {
+    WouldCycle,
+    NonexistentCell(CellId),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RemoveCellError {
+    NonexistentCell,
+    HasDependents,
+}
+
+/// Records what happened to a single cell during an eager propagation pass
+/// (`set_value`, `set_values`, `set_dependencies`), in the order it occurred.
+/// Collected only while tracing is enabled; see `Reactor::enable_tracing`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent<T> {
+    Recomputed { cell: ComputeCellId, old: T, new: T },
+    Skipped { cell: ComputeCellId },
+    CallbackFired { cell: ComputeCellId, value: T },
+}
+
 pub struct Reactor<'a, T> {
     compute_matrix: HashMap<CellId, Vec<ComputeCellId>>,
     compute_cells: HashMap<ComputeCellId, ComputeCell<'a, T>>,
     input_cell_values: HashMap<InputCellId, T>,
     next_cell_id: u32,
+    // When true, `set_value` only marks downstream cells dirty; recomputation is
+    // deferred to `value`/`stabilize` (the DCG-style demand-driven engine).
+    lazy: bool,
+    // When true, eager propagation (`recompute`/`fire_callbacks`) appends to `trace`.
+    tracing: bool,
+    trace: Vec<TraceEvent<T>>,
 }
 
 type ComputeFun<'a, T> = Box<dyn 'a + Fn(&[T]) -> T>;
 
 struct ComputeCell<'a, T> {
-    value: T,
+    // Cells so that `force` can bring a stale value up to date through a shared
+    // (`&self`) borrow — lazy recomputation is an implementation detail of reading
+    // a value, not a mutation callers should have to hold `&mut Reactor` for.
+    value: Cell<T>,
     compute_func: ComputeFun<'a, T>,
     dependencies: Vec<CellId>,
     callbacks: HashMap<CallbackId, Box<dyn 'a + FnMut(T)>>,
     next_callback_id: u32,
+    // Set by `set_value` in lazy mode; cleared once the cached `value` has been
+    // brought up to date. Always false in eager mode, since recomputation there
+    // happens inline.
+    dirty: Cell<bool>,
+    // This cell's value as of the last `stabilize` call. Unlike `dirty`, never
+    // cleared by a `value`/`force` peek — only `stabilize` itself updates it —
+    // so it stays a valid baseline for detecting a change no matter how many
+    // times the cell was forced by reads in between.
+    baseline: T,
+    // Set alongside `dirty` whenever a lazy write marks this cell downstream of
+    // a change; unlike `dirty`, a `value` peek does not clear it. Tells
+    // `stabilize` which cells to check against `baseline`, even ones already
+    // forced (and so no longer `dirty`) by an intervening peek.
+    stale: bool,
 }
 
 // You are guaranteed that Reactor will only be tested against types that are Copy + PartialEq.
@@ -62,6 +108,19 @@ impl<'a, T: Copy + PartialEq> Reactor<'a, T> {
             compute_cells: HashMap::new(),
             input_cell_values: HashMap::new(),
             next_cell_id: 1,
+            lazy: false,
+            tracing: false,
+            trace: Vec::new(),
+        }
+    }
+
+    // Like `new`, but switches the reactor into demand-driven (DCG) mode: `set_value`
+    // only marks downstream compute cells dirty, and recomputation happens lazily the
+    // next time `value` or `stabilize` forces them.
+    pub fn new_lazy() -> Self {
+        Self {
+            lazy: true,
+            ..Self::new()
         }
     }
 
@@ -99,12 +158,16 @@ impl<'a, T: Copy + PartialEq> Reactor<'a, T> {
         let new_cell_id = ComputeCellId(self.next_cell_id);
         self.next_cell_id += 1;
 
+        let initial_value = compute_func(&self.values(dependencies));
         let compute_cell = ComputeCell {
-            value: compute_func(&self.values(dependencies)),
+            value: Cell::new(initial_value),
             compute_func: Box::new(compute_func),
             callbacks: HashMap::new(),
             dependencies: Vec::from(dependencies),
             next_callback_id: u32::default(),
+            dirty: Cell::new(false),
+            baseline: initial_value,
+            stale: false,
         };
         self.compute_cells.insert(new_cell_id, compute_cell);
 
@@ -125,11 +188,15 @@ impl<'a, T: Copy + PartialEq> Reactor<'a, T> {
     //
     // It turns out this introduces a significant amount of extra complexity to this exercise.
     // We chose not to cover this here, since this exercise is probably enough work as-is.
+    //
+    // In lazy mode a compute cell may be dirty, in which case fetching its value first
+    // forces it (and, recursively, any dirty dependencies) up to date.
     pub fn value(&self, id: CellId) -> Option<T> {
         match id {
-            CellId::Compute(compute_cell_id) => {
-                self.compute_cells.get(&compute_cell_id).map(|c| c.value)
-            }
+            CellId::Compute(compute_cell_id) => self
+                .compute_cells
+                .contains_key(&compute_cell_id)
+                .then(|| self.force(compute_cell_id)),
             CellId::Input(input_cell_id) => self.input_cell_values.get(&input_cell_id).copied(),
         }
     }
@@ -141,6 +208,82 @@ impl<'a, T: Copy + PartialEq> Reactor<'a, T> {
             .collect()
     }
 
+    // Brings a compute cell's cached value up to date if it is dirty, recursively
+    // forcing its dependencies first, and returns the (now current) cached value.
+    // Takes `&self`: the cached `value`/`dirty` fields are `Cell`s so that reading
+    // a lazily-computed value never needs a mutable borrow of the whole `Reactor`.
+    fn force(&self, compute_cell_id: ComputeCellId) -> T {
+        let compute_cell = &self.compute_cells[&compute_cell_id];
+        if compute_cell.dirty.get() {
+            let dependencies = compute_cell.dependencies.clone();
+            let values = self.values(&dependencies);
+            let compute_cell = &self.compute_cells[&compute_cell_id];
+            compute_cell.value.set((compute_cell.compute_func)(&values));
+            compute_cell.dirty.set(false);
+        }
+
+        self.compute_cells[&compute_cell_id].value.get()
+    }
+
+    // Marks every compute cell transitively downstream of `start` dirty, without
+    // recomputing anything (the lazy counterpart to the eager recompute loop below).
+    fn mark_dirty(&mut self, start: CellId) {
+        let dirty = self.dirty_set(start);
+        self.mark_stale(dirty);
+    }
+
+    // Marks each of `compute_cell_ids` dirty (so the next `force` recomputes it)
+    // and stale (so the next `stabilize` checks it against its `baseline`, even
+    // if an intervening `value` peek clears `dirty` first).
+    fn mark_stale(&mut self, compute_cell_ids: impl IntoIterator<Item = ComputeCellId>) {
+        for compute_cell_id in compute_cell_ids {
+            let compute_cell = self.compute_cells.get_mut(&compute_cell_id).unwrap();
+            compute_cell.dirty.set(true);
+            compute_cell.stale = true;
+        }
+    }
+
+    // Collects every `ComputeCellId` reachable from `start` by walking `compute_matrix`
+    // forward, i.e. the set of compute cells transitively downstream of `start`.
+    fn dirty_set(&self, start: CellId) -> HashSet<ComputeCellId> {
+        let mut dirty = HashSet::new();
+        let mut to_visit: VecDeque<ComputeCellId> = self
+            .compute_matrix
+            .get(&start)
+            .cloned()
+            .unwrap_or_default()
+            .into();
+
+        while let Some(compute_cell_id) = to_visit.pop_front() {
+            if dirty.insert(compute_cell_id) {
+                if let Some(downstream) = self.compute_matrix.get(&CellId::Compute(compute_cell_id)) {
+                    to_visit.extend(downstream.iter().copied());
+                }
+            }
+        }
+
+        dirty
+    }
+
+    // Builds in-degrees for `dirty`, counting only edges whose source is itself in `dirty`
+    // (i.e. ignoring the triggering input cell, which is always "ready").
+    fn in_degrees(&self, dirty: &HashSet<ComputeCellId>) -> HashMap<ComputeCellId, usize> {
+        let mut in_degree: HashMap<ComputeCellId, usize> =
+            dirty.iter().map(|&id| (id, 0)).collect();
+
+        for &compute_cell_id in dirty {
+            for dep in &self.compute_cells[&compute_cell_id].dependencies {
+                if let CellId::Compute(dep_id) = dep {
+                    if dirty.contains(dep_id) {
+                        *in_degree.get_mut(&compute_cell_id).unwrap() += 1;
+                    }
+                }
+            }
+        }
+
+        in_degree
+    }
+
     // Sets the value of the specified input cell.
     //
     // Returns false if the cell does not exist.
@@ -157,46 +300,189 @@ impl<'a, T: Copy + PartialEq> Reactor<'a, T> {
         self.input_cell_values
             .entry(input_cell_id)
             .and_modify(|value| *value = new_value);
-        let mut to_recompute: VecDeque<ComputeCellId> = VecDeque::from(
-            self.compute_matrix
-                .entry(CellId::Input(input_cell_id))
-                .or_default()
-                .clone(),
-        );
 
-        let mut maybe_changed = HashMap::new();
+        if self.lazy {
+            // Demand-driven mode: defer all recomputation to `value`/`stabilize`.
+            self.mark_dirty(CellId::Input(input_cell_id));
+            return true;
+        }
 
-        while !to_recompute.is_empty() {
-            let compute_cell_id = to_recompute.pop_front().unwrap();
-            let values = self.values(&self.compute_cells[&compute_cell_id].dependencies);
-            let compute_cell = self.compute_cells.get_mut(&compute_cell_id).unwrap();
-            maybe_changed
-                .entry(compute_cell_id)
-                .or_insert(compute_cell.value);
-            compute_cell.value = (compute_cell.compute_func)(&values);
+        let dirty = self.dirty_set(CellId::Input(input_cell_id));
+        let maybe_changed = self.recompute(dirty);
+        self.fire_callbacks(maybe_changed);
 
-            self.compute_matrix
+        true
+    }
+
+    // Applies several input changes as a single transaction: every write lands before any
+    // recomputation happens, and each affected compute cell's callbacks fire at most once,
+    // with its final value, instead of once per `set_value` in the batch.
+    //
+    // Returns false (applying nothing) if any `InputCellId` in `updates` does not exist.
+    pub fn set_values(&mut self, updates: &[(InputCellId, T)]) -> bool {
+        if updates
+            .iter()
+            .any(|(input_cell_id, _)| !self.input_cell_values.contains_key(input_cell_id))
+        {
+            return false;
+        }
+
+        for &(input_cell_id, new_value) in updates {
+            self.input_cell_values
+                .entry(input_cell_id)
+                .and_modify(|value| *value = new_value);
+        }
+
+        let dirty: HashSet<ComputeCellId> = updates
+            .iter()
+            .flat_map(|&(input_cell_id, _)| self.dirty_set(CellId::Input(input_cell_id)))
+            .collect();
+
+        if self.lazy {
+            self.mark_stale(dirty);
+        } else {
+            let maybe_changed = self.recompute(dirty);
+            self.fire_callbacks(maybe_changed);
+        }
+
+        true
+    }
+
+    // Schedules `dirty` topologically (Kahn's algorithm) so every cell in it recomputes
+    // exactly once, in dependency order, and returns the value each one held beforehand
+    // (for comparison by `fire_callbacks`).
+    fn recompute(&mut self, dirty: HashSet<ComputeCellId>) -> Vec<(ComputeCellId, T)> {
+        let mut in_degree = self.in_degrees(&dirty);
+        let mut ready: VecDeque<ComputeCellId> = in_degree
+            .iter()
+            .filter(|(_, &degree)| degree == 0)
+            .map(|(&compute_cell_id, _)| compute_cell_id)
+            .collect();
+
+        // Each dirty cell is visited exactly once (that's the point of the topological
+        // schedule), so recording its pre-recompute value in visit order, rather than in
+        // a HashMap, is both enough to dedupe and what lets `fire_callbacks` fire
+        // callbacks in the same order cells were actually recomputed.
+        let mut maybe_changed = Vec::new();
+
+        while let Some(compute_cell_id) = ready.pop_front() {
+            let dependencies = self.compute_cells[&compute_cell_id].dependencies.clone();
+            let values = self.values(&dependencies);
+            let compute_cell = &self.compute_cells[&compute_cell_id];
+            let old_value = compute_cell.value.get();
+            maybe_changed.push((compute_cell_id, old_value));
+            let new_value = (compute_cell.compute_func)(&values);
+            compute_cell.value.set(new_value);
+
+            if self.tracing {
+                let event = if old_value != new_value {
+                    TraceEvent::Recomputed {
+                        cell: compute_cell_id,
+                        old: old_value,
+                        new: new_value,
+                    }
+                } else {
+                    TraceEvent::Skipped {
+                        cell: compute_cell_id,
+                    }
+                };
+                self.trace.push(event);
+            }
+
+            for downstream in self
+                .compute_matrix
                 .entry(CellId::Compute(compute_cell_id))
                 .or_default()
-                .iter()
-                .for_each(|downstram| to_recompute.push_back(*downstram))
+                .clone()
+            {
+                if let Some(degree) = in_degree.get_mut(&downstream) {
+                    *degree -= 1;
+                    if *degree == 0 {
+                        ready.push_back(downstream);
+                    }
+                }
+            }
         }
 
+        maybe_changed
+    }
+
+    // Fires each recomputed cell's callbacks at most once, passing the final value, for
+    // those whose value actually changed relative to the `recompute` snapshot. Processes
+    // `maybe_changed` in the order `recompute` visited the cells, so `TraceEvent`s stay in
+    // actual propagation order.
+    fn fire_callbacks(&mut self, maybe_changed: Vec<(ComputeCellId, T)>) {
         maybe_changed
             .into_iter()
             .for_each(|(compute_cell_id, old_value)| {
                 let new_value = self.value(CellId::Compute(compute_cell_id)).unwrap();
                 if old_value != new_value {
-                    self.compute_cells
-                        .get_mut(&compute_cell_id)
-                        .unwrap()
+                    let compute_cell = self.compute_cells.get_mut(&compute_cell_id).unwrap();
+                    let callback_count = compute_cell.callbacks.len();
+                    compute_cell
                         .callbacks
                         .values_mut()
-                        .for_each(|callback| (*callback)(new_value))
+                        .for_each(|callback| (*callback)(new_value));
+
+                    if self.tracing {
+                        for _ in 0..callback_count {
+                            self.trace.push(TraceEvent::CallbackFired {
+                                cell: compute_cell_id,
+                                value: new_value,
+                            });
+                        }
+                    }
                 }
             });
+    }
 
-        true
+    // In lazy mode, forces every cell left stale by prior `set_value`/`set_values`/
+    // `set_dependencies` calls and fires callbacks for those whose value actually
+    // changed since the previous `stabilize`. A no-op in eager mode, since nothing
+    // is ever marked stale there.
+    //
+    // Checks `stale`, not `dirty`: a `value` peek between the write and this call
+    // already forces the cell and clears `dirty`, but must not hide the change —
+    // `stale` survives that peek, and `baseline` still holds the pre-write value
+    // to compare against.
+    pub fn stabilize(&mut self) {
+        let stale_ids: Vec<ComputeCellId> = self
+            .compute_cells
+            .iter()
+            .filter(|(_, compute_cell)| compute_cell.stale)
+            .map(|(&compute_cell_id, _)| compute_cell_id)
+            .collect();
+
+        for &compute_cell_id in &stale_ids {
+            self.force(compute_cell_id);
+        }
+
+        for compute_cell_id in stale_ids {
+            let compute_cell = self.compute_cells.get_mut(&compute_cell_id).unwrap();
+            let old_value = compute_cell.baseline;
+            let new_value = compute_cell.value.get();
+            compute_cell.baseline = new_value;
+            compute_cell.stale = false;
+
+            if old_value != new_value {
+                compute_cell
+                    .callbacks
+                    .values_mut()
+                    .for_each(|callback| (*callback)(new_value));
+            }
+        }
+    }
+
+    // Turns on trace recording for eager propagation (`set_value`, `set_values`,
+    // `set_dependencies`). Off by default, so the hot path does nothing extra.
+    pub fn enable_tracing(&mut self) {
+        self.tracing = true;
+    }
+
+    // Returns every `TraceEvent` recorded since the last call, leaving an empty trace
+    // behind. Recording must have been turned on via `enable_tracing` first.
+    pub fn take_trace(&mut self) -> Vec<TraceEvent<T>> {
+        std::mem::take(&mut self.trace)
     }
 
     // Adds a callback to the specified compute cell.
@@ -247,4 +533,126 @@ impl<'a, T: Copy + PartialEq> Reactor<'a, T> {
             None => Err(NonexistentCallback),
         }
     }
+
+    // Would wiring `cell` to depend on `new_deps` introduce a cycle? This is the case
+    // exactly when `cell` is already reachable (forward, through `compute_matrix`) from
+    // one of the `new_deps`, or `cell` appears among `new_deps` itself, since either
+    // would close a loop once the new dependency edges are added.
+    //
+    // Deliberately reuses forward-reachability (`dirty_set`) rather than a DFS
+    // white/gray/black coloring walk: the two are equivalent here (`cell` closes a
+    // loop iff it's reachable from one of `new_deps`), `dirty_set` is already the
+    // reactor's one forward-reachability primitive (also used by `mark_dirty` and
+    // `remove_cell`'s dependents check), and introducing a second traversal scheme
+    // for the same question would just be more code to keep in sync.
+    fn would_cycle(&self, cell: ComputeCellId, new_deps: &[CellId]) -> bool {
+        let reachable_from_cell = self.dirty_set(CellId::Compute(cell));
+        new_deps.iter().any(|&dep| match dep {
+            CellId::Compute(dep_id) => dep_id == cell || reachable_from_cell.contains(&dep_id),
+            CellId::Input(_) => false,
+        })
+    }
+
+    // Rewires a compute cell's dependencies and formula after creation.
+    //
+    // Returns `SetDependenciesError::NonexistentCell` (leaving the reactor unchanged) if
+    // any of `new_deps` doesn't exist, matching `create_compute`'s contract — this is
+    // reachable whenever a caller passes in a `CellId` a prior `remove_cell` invalidated.
+    //
+    // Returns `SetDependenciesError::WouldCycle` (leaving the reactor unchanged) if the
+    // new dependencies would create a cycle back to `cell`. Unlike `create_compute`, this
+    // has to check for cycles explicitly, since rewiring (unlike creation) can make a
+    // cell depend on something that now depends on it.
+    //
+    // Both checks run before any mutation, so a rejected call never leaves `cell` half
+    // rewired with an edge to a dependency that doesn't exist.
+    //
+    // On success, `cell` and everything downstream of it are recomputed and any
+    // callbacks whose value changed as a result are fired, exactly as in `set_value`.
+    pub fn set_dependencies<F: 'a + Fn(&[T]) -> T>(
+        &mut self,
+        cell: ComputeCellId,
+        new_deps: &[CellId],
+        new_func: F,
+    ) -> Result<(), SetDependenciesError> {
+        new_deps
+            .iter()
+            .find(|&&cell_id| self.value(cell_id).is_none())
+            .map_or(Ok(()), |&cell_id| Err(SetDependenciesError::NonexistentCell(cell_id)))?;
+
+        if self.would_cycle(cell, new_deps) {
+            return Err(SetDependenciesError::WouldCycle);
+        }
+
+        let old_deps = self.compute_cells[&cell].dependencies.clone();
+        for old_dep in old_deps {
+            if let Some(consumers) = self.compute_matrix.get_mut(&old_dep) {
+                consumers.retain(|&consumer| consumer != cell);
+            }
+        }
+        for &new_dep in new_deps {
+            self.compute_matrix.entry(new_dep).or_default().push(cell);
+        }
+
+        let compute_cell = self.compute_cells.get_mut(&cell).unwrap();
+        compute_cell.compute_func = Box::new(new_func);
+        compute_cell.dependencies = Vec::from(new_deps);
+
+        let mut dirty = self.dirty_set(CellId::Compute(cell));
+        dirty.insert(cell);
+
+        if self.lazy {
+            self.mark_stale(dirty);
+        } else {
+            let maybe_changed = self.recompute(dirty);
+            self.fire_callbacks(maybe_changed);
+        }
+
+        Ok(())
+    }
+
+    // Removes an input or compute cell.
+    //
+    // Returns `RemoveCellError::HasDependents` if another compute cell still lists `id`
+    // among its dependencies (`compute_matrix` already tracks each cell's consumers, so
+    // this is the same check `would_cycle` relies on elsewhere) — only leaves of the
+    // consumer graph can be removed. Returns `RemoveCellError::NonexistentCell` if `id`
+    // doesn't exist.
+    //
+    // On success, drops the cell (and, for a compute cell, its callbacks), and removes it
+    // from every dependency's `compute_matrix` entry so later recomputation never visits
+    // the stale id.
+    pub fn remove_cell(&mut self, id: CellId) -> Result<(), RemoveCellError> {
+        if self
+            .compute_matrix
+            .get(&id)
+            .is_some_and(|consumers| !consumers.is_empty())
+        {
+            return Err(RemoveCellError::HasDependents);
+        }
+
+        match id {
+            CellId::Input(input_cell_id) => {
+                self.input_cell_values
+                    .remove(&input_cell_id)
+                    .ok_or(RemoveCellError::NonexistentCell)?;
+            }
+            CellId::Compute(compute_cell_id) => {
+                let compute_cell = self
+                    .compute_cells
+                    .remove(&compute_cell_id)
+                    .ok_or(RemoveCellError::NonexistentCell)?;
+
+                for dependency in compute_cell.dependencies {
+                    if let Some(consumers) = self.compute_matrix.get_mut(&dependency) {
+                        consumers.retain(|&consumer| consumer != compute_cell_id);
+                    }
+                }
+            }
+        }
+
+        self.compute_matrix.remove(&id);
+
+        Ok(())
+    }
 }